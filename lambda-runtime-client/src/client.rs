@@ -0,0 +1,27 @@
+//! Defines the `RuntimeClient` trait implemented by the HTTP client(s) that poll
+//! the Lambda Runtime API, and the event type they hand back to the runtime.
+use crate::error::ApiError;
+
+/// A single invocation fetched from the `next_event` Runtime API. Implementations
+/// echo `request_id` back on the matching `event_response`/`event_error` call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NextEventResponse {
+    /// The invocation's request id, from the `Lambda-Runtime-Aws-Request-Id` header
+    pub request_id: String,
+    /// The raw event payload to hand to the function handler
+    pub event_body: Vec<u8>,
+}
+
+/// Implemented by the HTTP client(s) that talk to the Lambda Runtime API.
+///
+/// Implementations should return an `ApiError` whose `is_recoverable()` accurately
+/// reflects transient (retryable) vs. permanent failures — `RetryingRuntimeClient`
+/// relies on it to decide what's safe to retry.
+pub trait RuntimeClient {
+    /// Blocks until the next invocation event is available.
+    fn next_event(&self) -> Result<NextEventResponse, ApiError>;
+    /// Reports a successful invocation result for `request_id`.
+    fn event_response(&self, request_id: &str, response: Vec<u8>) -> Result<(), ApiError>;
+    /// Reports a handler error for `request_id`.
+    fn event_error(&self, request_id: &str, error: &ApiError) -> Result<(), ApiError>;
+}
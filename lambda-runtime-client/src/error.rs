@@ -1,13 +1,21 @@
 //! This module defines the `RuntimeApiError` trait that developers should implement
 //! to send their custom errors to the AWS Lambda Runtime Client SDK. The module also
 //! defines the `ApiError` type returned by the `RuntimeClient` implementations.
-use failure::{AsFail, Backtrace, Context, Fail};
+//!
+//! Stack trace collection is gated behind the `backtrace` cargo feature; disable it
+//! in size-sensitive deployments to compile the capture and formatting logic out entirely.
+//! Requires a corresponding `backtrace = []` entry (default or opt-in, as the crate
+//! prefers) in this crate's `Cargo.toml` — the `#[cfg(feature = "backtrace")]` gates
+//! below are inert without it.
+use failure::{AsFail, Backtrace, Compat, Context, Error, Fail};
 use lambda_runtime_errors::LambdaErrorExt;
+#[cfg(feature = "backtrace")]
 use log::*;
 use serde_derive::*;
 use std::{
     fmt::{self, Display},
     option::Option,
+    time::Duration,
 };
 
 /// Error type for the error responses to the Runtime APIs. In the future, this library
@@ -31,6 +39,10 @@ pub struct ErrorResponse {
     /// this value is automatically populated using the `backtrace` crate.
     #[serde(rename = "stackTrace")]
     pub stack_trace: Option<Vec<String>>,
+    /// The `Display` output of every link in the failure's cause chain, outermost
+    /// first, collected via `Fail::iter_causes()`. Empty when the error has no cause.
+    #[serde(rename = "causes")]
+    pub causes: Vec<String>,
 }
 
 impl ErrorResponse {
@@ -44,35 +56,71 @@ impl ErrorResponse {
     /// * `err_type` An error type that identifies the root cause. Normally populated by the
     ///   `error_type()` method in the `LambdaErrorExt` trait.
     /// * `backtrace` The stack trace for the error
+    /// * `causes` The `Display` output of each link in the error's cause chain
     ///
     /// # Return
     /// A new instance of the `ErrorResponse` object.
-    fn new(message: String, err_type: String, backtrace: Option<&Backtrace>) -> Self {
-        let mut err = ErrorResponse {
+    fn new(message: String, err_type: String, backtrace: Option<&Backtrace>, causes: Vec<String>) -> Self {
+        ErrorResponse {
             error_message: message,
             error_type: err_type,
-            stack_trace: Option::default(),
-        };
-        // assume that failure is smart enough to only collect a backtrace
-        // if the env variable is enabled
-        if let Some(stack) = backtrace {
-            trace!("Begin backtrace collection");
-            err.stack_trace = Some(
-                format!("{:?}", stack)
-                    .lines()
-                    .map(std::string::ToString::to_string)
-                    .collect::<Vec<String>>(),
-            );
-            trace!("Completed backtrace collection");
+            stack_trace: Self::capture_stack_trace(backtrace),
+            causes,
         }
+    }
+
+    /// Collects the stack trace into the `Vec<String>` stored on `stack_trace`.
+    ///
+    /// When the `backtrace` feature is enabled (which needs a toolchain with
+    /// `std::backtrace`; there is no fallback for older toolchains), this prefers a freshly captured `std::backtrace::Backtrace` (which honors
+    /// `RUST_BACKTRACE`/`RUST_LIB_BACKTRACE` natively) and only falls back to the
+    /// `failure`-provided backtrace when the standard library didn't capture one.
+    #[cfg(feature = "backtrace")]
+    fn capture_stack_trace(backtrace: Option<&Backtrace>) -> Option<Vec<String>> {
+        trace!("Begin backtrace collection");
+        let std_backtrace = std::backtrace::Backtrace::capture();
+        let raw = if std_backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            Some(format!("{:?}", std_backtrace))
+        } else {
+            backtrace.map(|stack| format!("{:?}", stack))
+        };
+        trace!("Completed backtrace collection");
+        raw.map(|raw| raw.lines().map(std::string::ToString::to_string).collect::<Vec<String>>())
+    }
+
+    /// With the `backtrace` feature disabled, stack traces are never collected.
+    #[cfg(not(feature = "backtrace"))]
+    fn capture_stack_trace(_backtrace: Option<&Backtrace>) -> Option<Vec<String>> {
+        None
+    }
 
-        err
+    /// Renders the top-level message followed by its full cause chain, one link
+    /// per `: `, mirroring the alternate (`{:#}`) rendering `anyhow::Error` uses.
+    /// Useful for logging a single line that still carries the nested context.
+    pub fn chained_message(&self) -> String {
+        let mut rendered = self.error_message.clone();
+        for cause in &self.causes {
+            rendered.push_str(": ");
+            rendered.push_str(cause);
+        }
+        rendered
     }
 }
 
 impl<T: AsFail + LambdaErrorExt + Display> From<T> for ErrorResponse {
     fn from(e: T) -> Self {
-        ErrorResponse::new(format!("{}", e), e.error_type().to_owned(), e.as_fail().backtrace())
+        let fail = e.as_fail();
+        // `Compat<Error>` — the standard interop wrapper produced by `.compat()` —
+        // only gets `Fail` through `failure`'s blanket `StdError` impl, whose default
+        // `cause()` always returns `None`. Walking `fail`'s chain directly would
+        // silently drop the wrapped `Error`'s real causes, so unwrap back to it first.
+        let causes = match fail.downcast_ref::<Compat<Error>>() {
+            Some(compat) => compat.get_ref().as_fail().iter_causes(),
+            None => fail.iter_causes(),
+        }
+        .map(|cause| format!("{}", cause))
+        .collect::<Vec<String>>();
+        ErrorResponse::new(format!("{}", e), e.error_type().to_owned(), fail.backtrace(), causes)
     }
 }
 
@@ -83,26 +131,59 @@ pub struct ApiError {
 }
 
 impl ApiError {
-    /// Returns `true` if the API error is recoverable and should be retried
+    /// Returns `true` if the API error is recoverable and should be retried.
+    ///
+    /// Server-side errors (5xx) and throttling responses are recoverable; an
+    /// oversized payload is a permanent client-side mistake, and an `Unhandled`
+    /// response is treated conservatively as non-recoverable since its cause is
+    /// unknown.
     pub fn is_recoverable(&self) -> bool {
-        match *self.inner.get_context() {
-            ApiErrorKind::Recoverable(_) => true,
-            _ => false,
+        match self.inner.get_context() {
+            ApiErrorKind::HttpError { status, .. } => *status >= 500,
+            ApiErrorKind::Throttled { .. } => true,
+            ApiErrorKind::PayloadTooLarge | ApiErrorKind::Unhandled => false,
+        }
+    }
+
+    /// Returns how long the caller should wait before retrying, if the Runtime API
+    /// supplied a hint. Only `ApiErrorKind::Throttled` carries this information.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self.inner.get_context() {
+            ApiErrorKind::Throttled { retry_after } => *retry_after,
+            _ => None,
         }
     }
 }
-/// Failure context for the `ApiError` type. The kind is used to indicate whether the
-/// error is recoverable and should be retried or not.
+/// Failure context for the `ApiError` type. The kind captures enough of the
+/// underlying Runtime API response (HTTP status, request id, throttling hints) for
+/// callers to make correct retry decisions instead of guessing from a string.
 #[derive(Clone, PartialEq, Debug, Fail)]
 pub enum ApiErrorKind {
-    /// Runtime implementations that receive recoverable errors should automatically
-    /// retry requests
-    #[fail(display = "Recoverable API error: {}", _0)]
-    Recoverable(String),
-    /// Unrecoverable error should cause the runtime implementation to call the `fail_init`
-    /// method of the Runtime APIs if it is appropriate and then shutdown gracefully
-    #[fail(display = "Unrecoverable API error: {}", _0)]
-    Unrecoverable(String),
+    /// The Runtime API responded with a non-2xx HTTP status. Only 5xx statuses
+    /// (see `ApiError::is_recoverable`) are transient; 4xx ones are permanent.
+    #[fail(display = "HTTP error (status {})", status)]
+    HttpError {
+        /// The HTTP status code returned by the Runtime API
+        status: u16,
+        /// The `Lambda-Runtime-Aws-Request-Id` header, when the response carried one
+        request_id: Option<String>,
+    },
+    /// The Runtime API is throttling requests. Recoverable, and `retry_after` should
+    /// be honored when present.
+    #[fail(display = "Throttled by the Runtime API")]
+    Throttled {
+        /// How long to wait before retrying, if the Runtime API provided a hint
+        retry_after: Option<Duration>,
+    },
+    /// The response exceeded the Runtime API's payload size limit. This is a
+    /// permanent client-side error and should cause the runtime implementation to
+    /// call `fail_init` (if appropriate) and shut down gracefully rather than retry.
+    #[fail(display = "Response payload exceeded the Runtime API size limit")]
+    PayloadTooLarge,
+    /// An error from the underlying HTTP client that doesn't map to a known case
+    /// above. Treated as unrecoverable until proven otherwise.
+    #[fail(display = "Unhandled Runtime API error")]
+    Unhandled,
 }
 
 impl Fail for ApiError {
@@ -144,7 +225,7 @@ impl From<Context<ApiErrorKind>> for ApiError {
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-    use failure::format_err;
+    use failure::{format_err, ResultExt};
     use std::env;
 
     #[test]
@@ -155,11 +236,55 @@ pub(crate) mod tests {
         assert_eq!(resp_err.stack_trace, None);
     }
 
+    #[test]
+    fn causes_and_chained_message_reflect_the_full_cause_chain() {
+        let root: Result<(), failure::Error> = Err(format_err!("root cause"));
+        let middle: failure::Error = root.context("middle layer").unwrap_err().into();
+        let top: failure::Error = Err::<(), _>(middle).context("top layer").unwrap_err().into();
+
+        let resp_err = ErrorResponse::from(top.compat());
+
+        assert_eq!(vec!["middle layer".to_owned(), "root cause".to_owned()], resp_err.causes);
+        assert_eq!("top layer: middle layer: root cause", resp_err.chained_message());
+    }
+
     #[test]
     fn is_recoverable_eq_correctly() {
-        let rec_err = ApiError::from(ApiErrorKind::Recoverable("Some recoverable kind".to_owned()));
-        assert_eq!(true, rec_err.is_recoverable());
-        let unrec_err = ApiError::from(ApiErrorKind::Unrecoverable("Some unrecovrable kind".to_owned()));
-        assert_eq!(false, unrec_err.is_recoverable());
+        let server_err = ApiError::from(ApiErrorKind::HttpError {
+            status: 503,
+            request_id: None,
+        });
+        assert_eq!(true, server_err.is_recoverable());
+
+        let client_err = ApiError::from(ApiErrorKind::HttpError {
+            status: 400,
+            request_id: Some("req-1".to_owned()),
+        });
+        assert_eq!(false, client_err.is_recoverable());
+
+        let throttled_err = ApiError::from(ApiErrorKind::Throttled {
+            retry_after: Some(Duration::from_secs(1)),
+        });
+        assert_eq!(true, throttled_err.is_recoverable());
+
+        let too_large_err = ApiError::from(ApiErrorKind::PayloadTooLarge);
+        assert_eq!(false, too_large_err.is_recoverable());
+
+        let unhandled_err = ApiError::from(ApiErrorKind::Unhandled);
+        assert_eq!(false, unhandled_err.is_recoverable());
+    }
+
+    #[test]
+    fn retry_after_only_present_on_throttled() {
+        let throttled_err = ApiError::from(ApiErrorKind::Throttled {
+            retry_after: Some(Duration::from_millis(250)),
+        });
+        assert_eq!(Some(Duration::from_millis(250)), throttled_err.retry_after());
+
+        let server_err = ApiError::from(ApiErrorKind::HttpError {
+            status: 500,
+            request_id: None,
+        });
+        assert_eq!(None, server_err.retry_after());
     }
 }
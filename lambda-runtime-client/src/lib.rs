@@ -0,0 +1,12 @@
+//! The AWS Lambda Runtime Client SDK. This crate defines the `RuntimeClient` interface
+//! for clients of the
+//! [Lambda Runtime APIs](https://docs.aws.amazon.com/lambda/latest/dg/runtimes-custom.html#runtimes-custom-build)
+//! along with the error and retry types shared by the runtime implementations that
+//! use it. It does not itself contain an HTTP client.
+pub mod client;
+pub mod error;
+pub mod retry;
+
+pub use crate::client::{NextEventResponse, RuntimeClient};
+pub use crate::error::{ApiError, ApiErrorKind, ErrorResponse, RUNTIME_ERROR_TYPE};
+pub use crate::retry::{RetryPolicy, RetryingRuntimeClient};
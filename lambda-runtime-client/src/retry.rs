@@ -0,0 +1,311 @@
+//! Retry support for calls to the Lambda Runtime API. [`RetryingRuntimeClient`]
+//! wraps any `RuntimeClient` so that its calls go through [`retry_with_backoff`],
+//! which consults `ApiError::is_recoverable()` to decide whether a failure is worth
+//! retrying at all, so only the kinds of errors documented as recoverable on
+//! `ApiErrorKind` are ever retried.
+use crate::{
+    client::{NextEventResponse, RuntimeClient},
+    error::ApiError,
+};
+use log::*;
+use std::{
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Configures how many times, and how long, the runtime poll loop waits between
+/// retries of a recoverable Runtime API error.
+///
+/// Delays grow exponentially from `base_delay`, capped at `max_delay`, with a small
+/// amount of jitter applied so concurrent executions don't retry in lockstep. An
+/// `ApiError::retry_after()` hint from the Runtime API, when present, takes
+/// precedence over the computed backoff.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first, before giving up.
+    pub max_attempts: u32,
+    /// Delay before the first retry; later retries double this, up to `max_delay`.
+    pub base_delay: Duration,
+    /// Upper bound on the computed backoff delay.
+    pub max_delay: Duration,
+    /// Total time budget across all attempts. `None` means no overall deadline.
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// The default policy: up to 3 attempts, starting at 50ms and doubling up to
+    /// 1s, with no overall deadline.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(1),
+            deadline: None,
+        }
+    }
+
+    /// A policy that retries immediately with no delay between attempts. Intended
+    /// for tests that exercise the retry loop without slowing down the test suite.
+    pub fn no_delay(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(0),
+            max_delay: Duration::from_millis(0),
+            deadline: None,
+        }
+    }
+
+    fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        // `attempt` is 1 for the first retry, so the exponent must start at 0.
+        let exponent = attempt.saturating_sub(1).min(16);
+        let exponential = self.base_delay.saturating_mul(1 << exponent);
+        apply_jitter(exponential).min(self.max_delay)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Applies up to 25% positive jitter to `delay`, seeded from the current time so
+/// that retries across concurrently executing invocations don't synchronize.
+fn apply_jitter(delay: Duration) -> Duration {
+    if delay.is_zero() {
+        return delay;
+    }
+    let subsec_nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.subsec_nanos())
+        .unwrap_or(0);
+    let jitter_fraction = f64::from(subsec_nanos % 1_000) / 1_000.0 * 0.25;
+    delay + delay.mul_f64(jitter_fraction)
+}
+
+/// Runs `call`, retrying according to `policy` whenever it returns a recoverable
+/// `ApiError`. Honors an `ApiError::retry_after()` hint over the policy's own
+/// backoff when the error carries one, and returns the final error unchanged once
+/// the attempt count or deadline is exhausted.
+pub fn retry_with_backoff<T, F>(policy: &RetryPolicy, mut call: F) -> Result<T, ApiError>
+where
+    F: FnMut() -> Result<T, ApiError>,
+{
+    let start = Instant::now();
+    let mut attempt = 0;
+    loop {
+        match call() {
+            Ok(value) => return Ok(value),
+            Err(err) => {
+                attempt += 1;
+                let attempts_exhausted = attempt >= policy.max_attempts;
+                let deadline_exhausted = policy
+                    .deadline
+                    .map(|deadline| start.elapsed() >= deadline)
+                    .unwrap_or(false);
+                if !err.is_recoverable() || attempts_exhausted || deadline_exhausted {
+                    return Err(err);
+                }
+                let delay = err.retry_after().unwrap_or_else(|| policy.delay_for_attempt(attempt));
+                warn!(
+                    "Recoverable Runtime API error on attempt {}, retrying in {:?}: {}",
+                    attempt, delay, err
+                );
+                if !delay.is_zero() {
+                    thread::sleep(delay);
+                }
+            }
+        }
+    }
+}
+
+/// A `RuntimeClient` that retries each call against `inner` according to `policy`.
+/// This is what actually applies the retry behavior described on
+/// `ApiError::is_recoverable()` to the runtime poll loop — wrap the HTTP client the
+/// runtime would otherwise call directly.
+pub struct RetryingRuntimeClient<C> {
+    inner: C,
+    policy: RetryPolicy,
+}
+
+impl<C: RuntimeClient> RetryingRuntimeClient<C> {
+    /// Wraps `inner` so every call retries according to `policy`.
+    pub fn new(inner: C, policy: RetryPolicy) -> Self {
+        RetryingRuntimeClient { inner, policy }
+    }
+}
+
+impl<C: RuntimeClient> RuntimeClient for RetryingRuntimeClient<C> {
+    fn next_event(&self) -> Result<NextEventResponse, ApiError> {
+        retry_with_backoff(&self.policy, || self.inner.next_event())
+    }
+
+    fn event_response(&self, request_id: &str, response: Vec<u8>) -> Result<(), ApiError> {
+        retry_with_backoff(&self.policy, || self.inner.event_response(request_id, response.clone()))
+    }
+
+    fn event_error(&self, request_id: &str, error: &ApiError) -> Result<(), ApiError> {
+        retry_with_backoff(&self.policy, || self.inner.event_error(request_id, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiErrorKind;
+    use std::cell::Cell;
+
+    #[test]
+    fn retries_recoverable_errors_until_success() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy::no_delay(5);
+        let result = retry_with_backoff(&policy, || {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(ApiError::from(ApiErrorKind::HttpError {
+                    status: 503,
+                    request_id: None,
+                }))
+            } else {
+                Ok(attempts.get())
+            }
+        });
+        assert_eq!(3, result.unwrap());
+        assert_eq!(3, attempts.get());
+    }
+
+    #[test]
+    fn does_not_retry_unrecoverable_errors() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy::no_delay(5);
+        let result: Result<(), ApiError> = retry_with_backoff(&policy, || {
+            attempts.set(attempts.get() + 1);
+            Err(ApiError::from(ApiErrorKind::PayloadTooLarge))
+        });
+        assert!(result.is_err());
+        assert_eq!(1, attempts.get());
+    }
+
+    #[test]
+    fn first_retry_delay_matches_base_delay_not_doubled() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            deadline: None,
+        };
+        let delay = policy.delay_for_attempt(1);
+        assert!(delay >= Duration::from_millis(100), "expected >= base_delay, got {:?}", delay);
+        assert!(delay <= Duration::from_millis(125), "expected <= 25% jitter over base_delay, got {:?}", delay);
+    }
+
+    #[test]
+    fn delay_never_exceeds_max_delay_after_jitter() {
+        let policy = RetryPolicy {
+            max_attempts: 20,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(200),
+            deadline: None,
+        };
+        // a large attempt number pushes the exponential delay far past max_delay;
+        // jitter is applied before clamping, so the clamped result still fits
+        let delay = policy.delay_for_attempt(10);
+        assert!(delay <= Duration::from_millis(200), "expected delay clamped to max_delay, got {:?}", delay);
+    }
+
+    #[test]
+    fn gives_up_once_max_attempts_reached() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy::no_delay(3);
+        let result: Result<(), ApiError> = retry_with_backoff(&policy, || {
+            attempts.set(attempts.get() + 1);
+            Err(ApiError::from(ApiErrorKind::HttpError {
+                status: 500,
+                request_id: None,
+            }))
+        });
+        assert!(result.is_err());
+        assert_eq!(3, attempts.get());
+    }
+
+    #[test]
+    fn gives_up_once_deadline_exceeded() {
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy {
+            max_attempts: 1000,
+            base_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(5),
+            deadline: Some(Duration::from_millis(12)),
+        };
+        let result: Result<(), ApiError> = retry_with_backoff(&policy, || {
+            attempts.set(attempts.get() + 1);
+            Err(ApiError::from(ApiErrorKind::HttpError {
+                status: 500,
+                request_id: None,
+            }))
+        });
+        assert!(result.is_err());
+        assert!(
+            attempts.get() < 1000,
+            "expected the deadline to cut the loop short, got {} attempts",
+            attempts.get()
+        );
+    }
+
+    struct FlakyClient {
+        failures_before_success: u32,
+        attempts: Cell<u32>,
+    }
+
+    impl RuntimeClient for FlakyClient {
+        fn next_event(&self) -> Result<NextEventResponse, ApiError> {
+            self.attempts.set(self.attempts.get() + 1);
+            if self.attempts.get() <= self.failures_before_success {
+                Err(ApiError::from(ApiErrorKind::HttpError {
+                    status: 503,
+                    request_id: None,
+                }))
+            } else {
+                Ok(NextEventResponse {
+                    request_id: "req-1".to_owned(),
+                    event_body: Vec::new(),
+                })
+            }
+        }
+
+        fn event_response(&self, _request_id: &str, _response: Vec<u8>) -> Result<(), ApiError> {
+            Ok(())
+        }
+
+        fn event_error(&self, _request_id: &str, _error: &ApiError) -> Result<(), ApiError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn retrying_runtime_client_retries_next_event_until_success() {
+        let client = RetryingRuntimeClient::new(
+            FlakyClient {
+                failures_before_success: 2,
+                attempts: Cell::new(0),
+            },
+            RetryPolicy::no_delay(5),
+        );
+        let event = client.next_event().expect("should eventually succeed");
+        assert_eq!("req-1", event.request_id);
+        assert_eq!(3, client.inner.attempts.get());
+    }
+
+    #[test]
+    fn retrying_runtime_client_gives_up_after_max_attempts() {
+        let client = RetryingRuntimeClient::new(
+            FlakyClient {
+                failures_before_success: 10,
+                attempts: Cell::new(0),
+            },
+            RetryPolicy::no_delay(3),
+        );
+        assert!(client.next_event().is_err());
+        assert_eq!(3, client.inner.attempts.get());
+    }
+}
@@ -0,0 +1,193 @@
+//! Companion derive for the `LambdaErrorExt` trait (the `RuntimeApiError` trait
+//! mentioned in `lambda-runtime-client`'s error module), which is what unlocks the
+//! blanket `From<T> for ErrorResponse` conversion for a custom error type.
+//!
+//! `#[derive(RuntimeApiError)]` generates `error_type()` from the type name (for a
+//! struct) or each variant name (for an enum), so implementors no longer have to
+//! hand-write it. The generated string can be overridden per type or per variant
+//! with `#[lambda_error(type = "...")]`. An enum variant (or a whole struct) can
+//! also be tagged `#[lambda_error(recoverable)]`, which does two things: it's
+//! reflected in the generated `is_recoverable()` method, and it drives the
+//! generated `From<Self> for lambda_runtime_client::ApiErrorKind`, which maps a
+//! tagged variant onto `ApiErrorKind::HttpError { status: 503, .. }` (recoverable)
+//! and everything else onto `ApiErrorKind::Unhandled`. That conversion is what lets
+//! a caller do `ApiError::from(ApiErrorKind::from(my_error))` and get a correctly
+//! retryable `ApiError` out of it.
+//!
+//! This crate only emits the derive; callers still implement `Display` (and, for
+//! enums with data, `Fail`) themselves, same as with `failure_derive`'s `Fail`.
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Lit, Meta, MetaNameValue, NestedMeta};
+
+const ATTR_NAME: &str = "lambda_error";
+
+#[proc_macro_derive(RuntimeApiError, attributes(lambda_error))]
+pub fn derive_runtime_api_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(expand(&input).unwrap_or_else(|err| err.to_compile_error()))
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let error_type_body = error_type_body(input, ident)?;
+    let is_recoverable_body = is_recoverable_body(input, ident)?;
+    let api_error_kind_body = api_error_kind_body(input, ident)?;
+
+    Ok(quote! {
+        impl #impl_generics lambda_runtime_errors::LambdaErrorExt for #ident #ty_generics #where_clause {
+            fn error_type(&self) -> &str {
+                #error_type_body
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Returns `true` if this error (or, for an enum, this variant) was
+            /// tagged `#[lambda_error(recoverable)]`.
+            pub fn is_recoverable(&self) -> bool {
+                #is_recoverable_body
+            }
+        }
+
+        impl #impl_generics ::std::convert::From<#ident #ty_generics> for lambda_runtime_client::ApiErrorKind #where_clause {
+            fn from(value: #ident #ty_generics) -> Self {
+                #api_error_kind_body
+            }
+        }
+    })
+}
+
+/// Builds the match (or literal, for a struct) that returns the `error_type()` string.
+fn error_type_body(input: &DeriveInput, ident: &Ident) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(_) => {
+            let type_name = override_type(&input.attrs).unwrap_or_else(|| input.ident.to_string());
+            Ok(quote! { #type_name })
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let type_name = override_type(&variant.attrs).unwrap_or_else(|| variant.ident.to_string());
+                let pattern = variant_pattern(ident, &variant.ident, &variant.fields);
+                quote! { #pattern => #type_name, }
+            });
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(unsupported_union(input)),
+    }
+}
+
+/// Builds the match (or literal, for a struct) that returns whether the error is
+/// recoverable, based on which variants carry `#[lambda_error(recoverable)]`.
+fn is_recoverable_body(input: &DeriveInput, ident: &Ident) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(_) => {
+            let recoverable = has_recoverable_attr(&input.attrs);
+            Ok(quote! { #recoverable })
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let recoverable = has_recoverable_attr(&variant.attrs);
+                let pattern = variant_pattern(ident, &variant.ident, &variant.fields);
+                quote! { #pattern => #recoverable, }
+            });
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(unsupported_union(input)),
+    }
+}
+
+/// Builds the match (or literal, for a struct) that converts a value of the
+/// derived type into the `ApiErrorKind` a recoverable variant should retry as.
+fn api_error_kind_body(input: &DeriveInput, ident: &Ident) -> syn::Result<TokenStream2> {
+    match &input.data {
+        Data::Struct(_) => {
+            let kind = api_error_kind_for(has_recoverable_attr(&input.attrs));
+            Ok(quote! { #kind })
+        }
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let kind = api_error_kind_for(has_recoverable_attr(&variant.attrs));
+                let pattern = variant_pattern(ident, &variant.ident, &variant.fields);
+                quote! { #pattern => #kind, }
+            });
+            Ok(quote! {
+                match value {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(_) => Err(unsupported_union(input)),
+    }
+}
+
+fn api_error_kind_for(recoverable: bool) -> TokenStream2 {
+    if recoverable {
+        quote! {
+            lambda_runtime_client::ApiErrorKind::HttpError {
+                status: 503,
+                request_id: None,
+            }
+        }
+    } else {
+        quote! { lambda_runtime_client::ApiErrorKind::Unhandled }
+    }
+}
+
+/// Builds a wildcard match pattern for one variant, qualified with `ident`
+/// (rather than `Self`) since some callers generate this pattern inside an impl
+/// whose `Self` is a different type (e.g. `impl From<#ident> for ApiErrorKind`).
+fn variant_pattern(ident: &Ident, variant_ident: &Ident, fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Unit => quote! { #ident::#variant_ident },
+        Fields::Unnamed(_) => quote! { #ident::#variant_ident(..) },
+        Fields::Named(_) => quote! { #ident::#variant_ident { .. } },
+    }
+}
+
+fn unsupported_union(input: &DeriveInput) -> syn::Error {
+    syn::Error::new_spanned(&input.ident, "#[derive(RuntimeApiError)] does not support unions")
+}
+
+/// Reads `#[lambda_error(type = "...")]` off a struct, enum, or variant, if present.
+fn override_type(attrs: &[syn::Attribute]) -> Option<String> {
+    lambda_error_meta_items(attrs).into_iter().find_map(|meta| match meta {
+        NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(value),
+            ..
+        })) if path.is_ident("type") => Some(value.value()),
+        _ => None,
+    })
+}
+
+/// Returns `true` if `#[lambda_error(recoverable)]` is present.
+fn has_recoverable_attr(attrs: &[syn::Attribute]) -> bool {
+    lambda_error_meta_items(attrs)
+        .into_iter()
+        .any(|meta| matches!(meta, NestedMeta::Meta(Meta::Path(path)) if path.is_ident("recoverable")))
+}
+
+fn lambda_error_meta_items(attrs: &[syn::Attribute]) -> Vec<NestedMeta> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(ATTR_NAME))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter().collect::<Vec<_>>()),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
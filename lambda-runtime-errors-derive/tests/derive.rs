@@ -0,0 +1,74 @@
+//! Integration tests for `#[derive(RuntimeApiError)]`, exercising struct vs. enum
+//! expansion, the `#[lambda_error(type = "...")]` override, and the `recoverable`
+//! tag's effect on both `is_recoverable()` and the generated `ApiErrorKind` conversion.
+use lambda_runtime_client::ApiErrorKind;
+use lambda_runtime_errors::LambdaErrorExt;
+use lambda_runtime_errors_derive::RuntimeApiError;
+
+#[derive(RuntimeApiError, Debug)]
+#[lambda_error(type = "ConfigError")]
+struct StructError;
+
+#[derive(RuntimeApiError, Debug)]
+enum HandlerError {
+    #[lambda_error(recoverable)]
+    UpstreamTimeout,
+    #[lambda_error(type = "BadInput")]
+    InvalidPayload(String),
+    Unexpected { code: u16 },
+}
+
+#[test]
+fn struct_uses_overridden_error_type_and_is_not_recoverable_by_default() {
+    let err = StructError;
+    assert_eq!("ConfigError", err.error_type());
+    assert!(!err.is_recoverable());
+    assert_eq!(ApiErrorKind::Unhandled, ApiErrorKind::from(err));
+}
+
+#[test]
+fn enum_variant_error_type_defaults_to_variant_name() {
+    assert_eq!("UpstreamTimeout", HandlerError::UpstreamTimeout.error_type());
+    assert_eq!("Unexpected", HandlerError::Unexpected { code: 500 }.error_type());
+}
+
+#[test]
+fn enum_variant_error_type_can_be_overridden() {
+    assert_eq!("BadInput", HandlerError::InvalidPayload("oops".to_owned()).error_type());
+}
+
+#[test]
+fn recoverable_variant_is_reflected_in_is_recoverable_and_api_error_kind() {
+    let recoverable = HandlerError::UpstreamTimeout;
+    assert!(recoverable.is_recoverable());
+    assert_eq!(
+        ApiErrorKind::HttpError {
+            status: 503,
+            request_id: None,
+        },
+        ApiErrorKind::from(recoverable)
+    );
+}
+
+#[test]
+fn untagged_variants_are_not_recoverable() {
+    let invalid = HandlerError::InvalidPayload("oops".to_owned());
+    assert!(!invalid.is_recoverable());
+    assert_eq!(ApiErrorKind::Unhandled, ApiErrorKind::from(invalid));
+
+    let unexpected = HandlerError::Unexpected { code: 500 };
+    assert!(!unexpected.is_recoverable());
+}
+
+#[test]
+fn enum_variant_fields_are_preserved() {
+    match HandlerError::InvalidPayload("oops".to_owned()) {
+        HandlerError::InvalidPayload(payload) => assert_eq!("oops", payload),
+        other => panic!("expected InvalidPayload, got {:?}", other),
+    }
+
+    match HandlerError::Unexpected { code: 500 } {
+        HandlerError::Unexpected { code } => assert_eq!(500, code),
+        other => panic!("expected Unexpected, got {:?}", other),
+    }
+}